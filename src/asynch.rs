@@ -0,0 +1,407 @@
+//! Async driver impl built on [embedded-hal-async]'s `I2c` trait.
+//!
+//! Enabled by the `async` feature. Shares [`Register`] and the public
+//! enums with the blocking drivers in [`crate::eh0_2`] / [`crate::eh1_0`],
+//! but owns its own struct since every method here is an `async fn`.
+//!
+//! [embedded-hal-async]: https://docs.rs/embedded-hal-async
+
+use crate::register::Register;
+use crate::{
+    I2CAddressSwitchState, Md22Error, OperatingMode, Speed, SpeedModeError,
+    PLAUSIBLE_SOFTWARE_REVISION,
+};
+use embedded_hal::i2c::{Error, ErrorKind};
+use embedded_hal_async::i2c::I2c;
+
+/// Async MD22 Driver, for use with Embassy executors and other
+/// current-generation async HALs.
+pub struct Md22Async<I2C> {
+    i2c: I2C,
+    mode: OperatingMode,
+    address: u8,
+}
+
+impl<I2C> Md22Async<I2C>
+where
+    I2C: I2c,
+{
+    /// Create a new MD22 driver from the given I2C peripheral and mode.
+    /// Zeroes acceleration and stops the motors (the mode-appropriate
+    /// stop value for Speed/Turn). Use
+    /// [`Md22Async::new_checked`] instead to also confirm a board is
+    /// present.
+    pub async fn new(i2c: I2C, mode: OperatingMode, address: I2CAddressSwitchState) -> Result<Self, I2C::Error> {
+        let mut md22 = Md22Async {
+            i2c,
+            mode,
+            address: address.bits()
+        };
+        md22.set_mode(mode).await?;
+        md22.set_acceleration(0).await?;
+        md22.set_speed(mode.stop_byte()).await?;
+        md22.set_turn(mode.stop_byte()).await?;
+
+        Ok(md22)
+    }
+
+    /// Like [`Md22Async::new`], but additionally reads back the software
+    /// revision to confirm a board is present: an
+    /// [`ErrorKind::NoAcknowledge`] is reported as
+    /// [`Md22Error::NotResponding`], any other transport fault as
+    /// [`Md22Error::Bus`], and an implausible revision as
+    /// [`Md22Error::UnexpectedDevice`].
+    pub async fn new_checked(i2c: I2C, mode: OperatingMode, address: I2CAddressSwitchState) -> Result<Self, Md22Error<I2C::Error>> {
+        let mut md22 = Self::new(i2c, mode, address).await?;
+
+        let revision = md22.get_software_revision().await.map_err(|e| match e.kind() {
+            ErrorKind::NoAcknowledge(_) => Md22Error::NotResponding,
+            _ => Md22Error::Bus(e),
+        })?;
+        if !PLAUSIBLE_SOFTWARE_REVISION.contains(&revision) {
+            return Err(Md22Error::UnexpectedDevice);
+        }
+
+        Ok(md22)
+    }
+
+    /// Re-assert mode, acceleration, speed, and turn to known-good
+    /// defaults (the current mode, with acceleration zeroed and the
+    /// motors stopped), for use after a detected NACK or other bus fault
+    /// leaves the board in an unknown state.
+    pub async fn recover(&mut self) -> Result<(), I2C::Error> {
+        let mode = self.mode;
+        self.set_mode(mode).await?;
+        self.set_acceleration(0).await?;
+        self.set_speed(mode.stop_byte()).await?;
+        self.set_turn(mode.stop_byte()).await?;
+        Ok(())
+    }
+
+    /// Set the operating mode.
+    pub async fn set_mode(&mut self, mode: OperatingMode) -> Result<(), I2C::Error> {
+        self.write_register(Register::Mode, mode as u8).await?;
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Set the motor speed register to the specified value.
+    pub async fn set_speed(&mut self, speed: u8) -> Result<(), I2C::Error> {
+        self.write_register(Register::Speed, speed).await
+    }
+
+    /// Set the motor turn register to the specified value.
+    pub async fn set_turn(&mut self, turn: u8) -> Result<(), I2C::Error> {
+        self.write_register(Register::Turn, turn).await
+    }
+
+    /// Set the motor speed register from a [`Speed`], checking that its
+    /// signedness matches the current [`OperatingMode`].
+    pub async fn set_speed_value(&mut self, speed: Speed) -> Result<(), SpeedModeError<I2C::Error>> {
+        if speed.is_signed() != self.mode.is_signed() {
+            return Err(SpeedModeError::ModeMismatch);
+        }
+        self.set_speed(speed.to_byte()).await?;
+        Ok(())
+    }
+
+    /// Set the motor speed register to a signed value, for use in
+    /// Mode1/Mode3.
+    pub async fn set_speed_signed(&mut self, speed: i8) -> Result<(), SpeedModeError<I2C::Error>> {
+        self.set_speed_value(Speed::Signed(speed)).await
+    }
+
+    /// Set the motor turn register from a [`Speed`], checking that its
+    /// signedness matches the current [`OperatingMode`].
+    pub async fn set_turn_value(&mut self, turn: Speed) -> Result<(), SpeedModeError<I2C::Error>> {
+        if turn.is_signed() != self.mode.is_signed() {
+            return Err(SpeedModeError::ModeMismatch);
+        }
+        self.set_turn(turn.to_byte()).await?;
+        Ok(())
+    }
+
+    /// Set the motor turn register to a signed value, for use in
+    /// Mode1/Mode3.
+    pub async fn set_turn_signed(&mut self, turn: i8) -> Result<(), SpeedModeError<I2C::Error>> {
+        self.set_turn_value(Speed::Signed(turn)).await
+    }
+
+    /// Set the motor acceleration register to the specified value.
+    /// The acceleration time is given by this value * 64us * n_steps.
+    pub async fn set_acceleration(&mut self, acceleration: u8) -> Result<(), I2C::Error> {
+        self.write_register(Register::Acceleration, acceleration).await
+    }
+
+    pub async fn get_software_revision(&mut self) -> Result<u8, I2C::Error> {
+        self.read_register(Register::SoftwareRevision).await
+    }
+
+    /// Read back the last-latched value of the Speed register.
+    pub async fn get_speed(&mut self) -> Result<u8, I2C::Error> {
+        self.read_register(Register::Speed).await
+    }
+
+    /// Read back the last-latched value of the Turn register.
+    pub async fn get_turn(&mut self) -> Result<u8, I2C::Error> {
+        self.read_register(Register::Turn).await
+    }
+
+    /// Read back the last-latched value of the Acceleration register.
+    pub async fn get_acceleration(&mut self) -> Result<u8, I2C::Error> {
+        self.read_register(Register::Acceleration).await
+    }
+
+    /// Read back the last-latched value of the Mode register.
+    pub async fn get_mode(&mut self) -> Result<u8, I2C::Error> {
+        self.read_register(Register::Mode).await
+    }
+
+    async fn read_register(&mut self, register: Register) -> Result<u8, I2C::Error> {
+        let bytes = [register.addr()];
+        let mut buffer: [u8;1] = [0;1];
+        self.i2c.write_read(self.address, &bytes, &mut buffer).await?;
+        Ok(buffer[0])
+    }
+
+    async fn write_register(&mut self, register: Register, value: u8) -> Result<(), I2C::Error> {
+        assert!(!register.is_read_only(), "attempted to write the read-only {register:?} register");
+        let bytes = [register.addr(), value];
+        self.i2c.write(self.address, &bytes).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::NoAcknowledgeSource;
+    use embedded_hal_mock::eh1 as hal;
+
+    /// The writes `Md22::new`/`Md22Async::new` perform for
+    /// `OperatingMode::Mode0`: acceleration zeroed, Speed/Turn set to the
+    /// unsigned mode's stop value (128).
+    fn new_expectation(address: I2CAddressSwitchState) -> Vec<hal::i2c::Transaction> {
+        vec![
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 128]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 128]),
+        ]
+    }
+
+    #[test]
+    fn get_software_revision() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![255]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        assert_eq!(255, pollster::block_on(md22.get_software_revision()).unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn new_checked_rejects_implausible_revision() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![0xFF]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        assert!(matches!(
+            pollster::block_on(Md22Async::new_checked(i2c, OperatingMode::Mode0, address)),
+            Err(Md22Error::UnexpectedDevice)
+        ));
+        mock.done();
+    }
+
+    #[test]
+    fn new_checked_accepts_plausible_revision() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![1]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        assert!(pollster::block_on(Md22Async::new_checked(i2c, OperatingMode::Mode0, address)).is_ok());
+        mock.done();
+    }
+
+    #[test]
+    fn new_checked_reports_nack_as_not_responding() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(
+            hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![0])
+                .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+        );
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        assert!(matches!(
+            pollster::block_on(Md22Async::new_checked(i2c, OperatingMode::Mode0, address)),
+            Err(Md22Error::NotResponding)
+        ));
+        mock.done();
+    }
+
+    #[test]
+    fn new_checked_preserves_other_bus_faults() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(
+            hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![0])
+                .with_error(ErrorKind::ArbitrationLoss),
+        );
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        assert!(matches!(
+            pollster::block_on(Md22Async::new_checked(i2c, OperatingMode::Mode0, address)),
+            Err(Md22Error::Bus(_))
+        ));
+        mock.done();
+    }
+
+    #[test]
+    fn get_speed() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::Speed.addr()], vec![128]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        assert_eq!(128, pollster::block_on(md22.get_speed()).unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn get_turn() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::Turn.addr()], vec![128]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        assert_eq!(128, pollster::block_on(md22.get_turn()).unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn get_acceleration() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::Acceleration.addr()], vec![64]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        assert_eq!(64, pollster::block_on(md22.get_acceleration()).unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn get_mode() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::Mode.addr()], vec![1]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        assert_eq!(1, pollster::block_on(md22.get_mode()).unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn set_mode() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), OperatingMode::Mode1.bits()]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        pollster::block_on(md22.set_mode(OperatingMode::Mode1)).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_acceleration() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 255]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        pollster::block_on(md22.set_acceleration(255)).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_speed() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 255]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        pollster::block_on(md22.set_speed(255)).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_turn() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 255]));
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        pollster::block_on(md22.set_turn(255)).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_speed_signed() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let expectation = [
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), OperatingMode::Mode1.bits()]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), (-128i8) as u8]),
+        ];
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode1, address)).unwrap();
+        pollster::block_on(md22.set_speed_signed(-128)).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_speed_value_rejects_mode_mismatch() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let expectation = new_expectation(address);
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        assert!(matches!(
+            pollster::block_on(md22.set_speed_value(Speed::Signed(-1))),
+            Err(SpeedModeError::ModeMismatch)
+        ));
+        mock.done();
+    }
+
+    #[test]
+    fn recover() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.extend([
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 128]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 128]),
+        ]);
+        let i2c = hal::i2c::Mock::new(&expectation);
+        let mut mock = i2c.clone();
+        let mut md22 = pollster::block_on(Md22Async::new(i2c, OperatingMode::Mode0, address)).unwrap();
+        pollster::block_on(md22.recover()).unwrap();
+        mock.done();
+    }
+}
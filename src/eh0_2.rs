@@ -0,0 +1,408 @@
+//! Driver impl for [embedded-hal] 0.2's blocking I2C traits.
+//!
+//! Enabled by the `eh0_2` feature.
+//!
+//! [embedded-hal]: https://docs.rs/embedded-hal/0.2
+
+use crate::mutex::PortMutex;
+use crate::register::Register;
+use crate::{
+    I2CAddressSwitchState, Md22, Md22Error, OperatingMode, Speed, SpeedModeError,
+    PLAUSIBLE_SOFTWARE_REVISION,
+};
+use core::cell::RefCell;
+use core::fmt::Debug;
+use embedded_hal_02::blocking::i2c::{Write, WriteRead};
+
+impl<'a, I2C, E> Md22<'a, RefCell<I2C>>
+where
+    I2C: WriteRead<Error = E> + Write<Error = E>,
+    E: Debug,
+{
+    /// Create a new MD22 driver borrowing the given `RefCell`-wrapped I2C
+    /// peripheral. Pass the same `&RefCell` to another `Md22` (at a
+    /// different switch address) or to another driver's constructor to
+    /// share the bus; use [`Md22::with_mutex`] to plug in a different
+    /// [`PortMutex`](crate::mutex::PortMutex) instead.
+    /// Zeroes acceleration and stops the motors (the mode-appropriate
+    /// stop value for Speed/Turn). Use
+    /// [`Md22::new_checked`] instead to also confirm a board is present.
+    pub fn new(i2c: &'a RefCell<I2C>, mode: OperatingMode, address: I2CAddressSwitchState) -> Result<Self, E> {
+        Self::with_mutex(i2c, mode, address)
+    }
+
+    /// Like [`Md22::new`], but additionally reads back the software
+    /// revision to confirm a board is present. See
+    /// [`Md22::with_mutex_checked`] for how read failures are reported.
+    pub fn new_checked(i2c: &'a RefCell<I2C>, mode: OperatingMode, address: I2CAddressSwitchState) -> Result<Self, Md22Error<E>> {
+        Self::with_mutex_checked(i2c, mode, address)
+    }
+}
+
+impl<'a, I2C, M, E> Md22<'a, M>
+where
+    M: PortMutex<Port = I2C>,
+    I2C: WriteRead<Error = E> + Write<Error = E>,
+    E: Debug,
+{
+    /// Create a new MD22 driver borrowing the I2C bus behind the given,
+    /// already-constructed [`PortMutex`](crate::mutex::PortMutex), so
+    /// several MD22 boards at different switch addresses (or other chips)
+    /// can coexist on one peripheral by borrowing the same `&M`.
+    /// Zeroes acceleration and stops the motors (the mode-appropriate
+    /// stop value for Speed/Turn). Use
+    /// [`Md22::with_mutex_checked`] instead to also confirm a board is
+    /// present.
+    pub fn with_mutex(mutex: &'a M, mode: OperatingMode, address: I2CAddressSwitchState) -> Result<Self, E> {
+        let mut md22 = Md22 {
+            mutex,
+            mode,
+            address: address.bits()
+        };
+        md22.set_mode(mode)?;
+        md22.set_acceleration(0)?;
+        md22.set_speed(mode.stop_byte())?;
+        md22.set_turn(mode.stop_byte())?;
+
+        Ok(md22)
+    }
+
+    /// Like [`Md22::with_mutex`], but additionally reads back the
+    /// software revision to confirm a board is present. embedded-hal 0.2
+    /// has no standard way to tell a NACK apart from other transport
+    /// faults, so a failed read is always reported as
+    /// [`Md22Error::Bus`]; an implausible revision is
+    /// [`Md22Error::UnexpectedDevice`].
+    pub fn with_mutex_checked(mutex: &'a M, mode: OperatingMode, address: I2CAddressSwitchState) -> Result<Self, Md22Error<E>> {
+        let mut md22 = Self::with_mutex(mutex, mode, address)?;
+
+        let revision = md22.get_software_revision().map_err(Md22Error::Bus)?;
+        if !PLAUSIBLE_SOFTWARE_REVISION.contains(&revision) {
+            return Err(Md22Error::UnexpectedDevice);
+        }
+
+        Ok(md22)
+    }
+
+    /// Re-assert mode, acceleration, speed, and turn to known-good
+    /// defaults (the current mode, with acceleration zeroed and the
+    /// motors stopped), for use after a detected NACK or other bus fault
+    /// leaves the board in an unknown state.
+    pub fn recover(&mut self) -> Result<(), E> {
+        let mode = self.mode;
+        self.set_mode(mode)?;
+        self.set_acceleration(0)?;
+        self.set_speed(mode.stop_byte())?;
+        self.set_turn(mode.stop_byte())?;
+        Ok(())
+    }
+
+    /// Set the operating mode.
+    pub fn set_mode(&mut self, mode: OperatingMode) -> Result<(), E> {
+        self.write_register(Register::Mode, mode as u8)?;
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Set the motor speed register to the specified value.
+    pub fn set_speed(&mut self, speed: u8) -> Result<(), E> {
+        self.write_register(Register::Speed, speed)
+    }
+
+    /// Set the motor turn register to the specified value.
+    pub fn set_turn(&mut self, turn: u8) -> Result<(), E> {
+        self.write_register(Register::Turn, turn)
+    }
+
+    /// Set the motor speed register from a [`Speed`], checking that its
+    /// signedness matches the current [`OperatingMode`].
+    pub fn set_speed_value(&mut self, speed: Speed) -> Result<(), SpeedModeError<E>> {
+        if speed.is_signed() != self.mode.is_signed() {
+            return Err(SpeedModeError::ModeMismatch);
+        }
+        self.set_speed(speed.to_byte())?;
+        Ok(())
+    }
+
+    /// Set the motor speed register to a signed value, for use in
+    /// Mode1/Mode3.
+    pub fn set_speed_signed(&mut self, speed: i8) -> Result<(), SpeedModeError<E>> {
+        self.set_speed_value(Speed::Signed(speed))
+    }
+
+    /// Set the motor turn register from a [`Speed`], checking that its
+    /// signedness matches the current [`OperatingMode`].
+    pub fn set_turn_value(&mut self, turn: Speed) -> Result<(), SpeedModeError<E>> {
+        if turn.is_signed() != self.mode.is_signed() {
+            return Err(SpeedModeError::ModeMismatch);
+        }
+        self.set_turn(turn.to_byte())?;
+        Ok(())
+    }
+
+    /// Set the motor turn register to a signed value, for use in
+    /// Mode1/Mode3.
+    pub fn set_turn_signed(&mut self, turn: i8) -> Result<(), SpeedModeError<E>> {
+        self.set_turn_value(Speed::Signed(turn))
+    }
+
+    /// Set the motor acceleration register to the specified value.
+    /// The acceleration time is given by this value * 64us * n_steps.
+    pub fn set_acceleration(&mut self, acceleration: u8) -> Result<(), E> {
+        self.write_register(Register::Acceleration, acceleration)
+    }
+
+    pub fn get_software_revision(&mut self) -> Result<u8, E> {
+        self.read_register(Register::SoftwareRevision)
+    }
+
+    /// Read back the last-latched value of the Speed register.
+    pub fn get_speed(&mut self) -> Result<u8, E> {
+        self.read_register(Register::Speed)
+    }
+
+    /// Read back the last-latched value of the Turn register.
+    pub fn get_turn(&mut self) -> Result<u8, E> {
+        self.read_register(Register::Turn)
+    }
+
+    /// Read back the last-latched value of the Acceleration register.
+    pub fn get_acceleration(&mut self) -> Result<u8, E> {
+        self.read_register(Register::Acceleration)
+    }
+
+    /// Read back the last-latched value of the Mode register.
+    pub fn get_mode(&mut self) -> Result<u8, E> {
+        self.read_register(Register::Mode)
+    }
+
+    fn read_register(&mut self, register: Register) -> Result<u8, E> {
+        let bytes = [register.addr()];
+        let mut buffer: [u8;1] = [0;1];
+        let address = self.address;
+        self.mutex.lock(|i2c| i2c.write_read(address, &bytes, &mut buffer))?;
+        Ok(buffer[0])
+    }
+
+    fn write_register(&mut self, register: Register, value: u8) -> Result<(), E> {
+        assert!(!register.is_read_only(), "attempted to write the read-only {register:?} register");
+        let bytes = [register.addr(), value];
+        let address = self.address;
+        self.mutex.lock(|i2c| i2c.write(address, &bytes))?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use core::cell::RefCell;
+    use embedded_hal_mock::eh0 as hal;
+
+    /// The writes `Md22::new`/`Md22Async::new` perform for
+    /// `OperatingMode::Mode0`: acceleration zeroed, Speed/Turn set to the
+    /// unsigned mode's stop value (128).
+    fn new_expectation(address: I2CAddressSwitchState) -> Vec<hal::i2c::Transaction> {
+        vec![
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 128]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 128]),
+        ]
+    }
+
+    #[test]
+    fn get_software_revision() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![255]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        assert_eq!(255, md22.get_software_revision().unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn new_checked_rejects_implausible_revision() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![0xFF]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        assert!(matches!(
+            Md22::new_checked(&i2c, OperatingMode::Mode0, address),
+            Err(Md22Error::UnexpectedDevice)
+        ));
+        mock.done();
+    }
+
+    #[test]
+    fn new_checked_accepts_plausible_revision() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![1]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        assert!(Md22::new_checked(&i2c, OperatingMode::Mode0, address).is_ok());
+        mock.done();
+    }
+
+    #[test]
+    fn get_speed() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::Speed.addr()], vec![128]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        assert_eq!(128, md22.get_speed().unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn get_turn() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::Turn.addr()], vec![128]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        assert_eq!(128, md22.get_turn().unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn get_acceleration() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::Acceleration.addr()], vec![64]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        assert_eq!(64, md22.get_acceleration().unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn get_mode() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write_read(address.bits(), vec![Register::Mode.addr()], vec![1]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        assert_eq!(1, md22.get_mode().unwrap());
+        mock.done();
+    }
+
+    #[test]
+    fn set_mode() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), OperatingMode::Mode1.bits()]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        md22.set_mode(OperatingMode::Mode1).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_acceleration() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 255]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        md22.set_acceleration(255).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_speed() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 255]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        md22.set_speed(255).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_turn() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.push(hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 255]));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        md22.set_turn(255).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_speed_signed() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let expectation = [
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), OperatingMode::Mode1.bits()]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), (-128i8) as u8]),
+        ];
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode1, address).unwrap();
+        md22.set_speed_signed(-128).unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn set_speed_value_rejects_mode_mismatch() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let expectation = new_expectation(address);
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        assert!(matches!(
+            md22.set_speed_value(Speed::Signed(-1)),
+            Err(SpeedModeError::ModeMismatch)
+        ));
+        mock.done();
+    }
+
+    #[test]
+    fn recover() {
+        let address = I2CAddressSwitchState::OnOnOnOn;
+        let mut expectation = new_expectation(address);
+        expectation.extend([
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 128]),
+            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 128]),
+        ]);
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let mut md22 = Md22::new(&i2c, OperatingMode::Mode0, address).unwrap();
+        md22.recover().unwrap();
+        mock.done();
+    }
+
+    #[test]
+    fn shares_the_bus_with_a_second_board() {
+        let first = I2CAddressSwitchState::OnOnOnOn;
+        let second = I2CAddressSwitchState::OffOnOnOn;
+        let mut expectation = new_expectation(first);
+        expectation.extend(new_expectation(second));
+        let mut mock = hal::i2c::Mock::new(&expectation);
+        let i2c = RefCell::new(mock.clone());
+        let _first_md22 = Md22::new(&i2c, OperatingMode::Mode0, first).unwrap();
+        let _second_md22 = Md22::new(&i2c, OperatingMode::Mode0, second).unwrap();
+        mock.done();
+    }
+}
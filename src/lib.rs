@@ -1,6 +1,20 @@
 //! Platform-agnostic MD22 motor driver which uses I2C via
 //! [embedded-hal].
 //!
+//! Two generations of the blocking `Md22` driver are available behind
+//! cargo features, each in their own module so the register logic is
+//! shared while the trait bounds differ:
+//! - `eh0_2`: `embedded_hal_02::blocking::i2c::{Write, WriteRead}` from
+//!   embedded-hal 0.2, see [`eh0_2`].
+//! - `eh1_0`: [`embedded_hal::i2c::I2c`] from embedded-hal 1.0, see [`eh1_0`].
+//!
+//! Enable only the one your HAL implements: both add inherent methods to
+//! the same [`Md22`] type under incompatible trait bounds, so turning on
+//! `eh0_2` and `eh1_0` together fails to compile.
+//!
+//! An async driver built on [`embedded_hal_async::i2c::I2c`] is available
+//! behind the `async` feature, see [`asynch`].
+//!
 //! [embedded-hal]: https://docs.rs/embedded-hal
 
 #![forbid(unsafe_code)]
@@ -8,17 +22,21 @@
 
 
 mod register;
-use embedded_hal as hal;
-use crate::register::Register;
-use core::fmt::Debug;
-use hal::blocking::i2c::{Write, WriteRead};
+pub mod mutex;
 
+#[cfg(feature = "eh0_2")]
+pub mod eh0_2;
+#[cfg(feature = "eh1_0")]
+pub mod eh1_0;
+#[cfg(feature = "async")]
+pub mod asynch;
 
+pub use crate::register::Register;
 
 #[derive(Clone, Copy)]
 /// Device I2C bus address switch states
 pub enum I2CAddressSwitchState {
-    /// Switch 1 - On, Switch 2 - On, Switch 3 - On, Switch 4 - On, 
+    /// Switch 1 - On, Switch 2 - On, Switch 3 - On, Switch 4 - On,
     OnOnOnOn    = 0xB0,
     /// Switch 1 - Off, Switch 2 - On, Switch 3 - On, Switch 4 - On,
     OffOnOnOn   = 0xB2,
@@ -37,7 +55,7 @@ pub enum I2CAddressSwitchState {
 }
 
 impl I2CAddressSwitchState {
-    fn bits(self) -> u8 {
+    pub(crate) fn bits(self) -> u8 {
         self as u8
     }
 }
@@ -45,16 +63,27 @@ impl I2CAddressSwitchState {
 #[derive(Clone, Copy)]
 /// I2C operating mode
 pub enum OperatingMode {
-    /// (Default)  The meaning of the speed registers is literal speeds in the range of:  
+    /// (Default)  The meaning of the speed registers is literal speeds in the range of:
     /// - 0 (full reverse)
     /// - 128 (stop)
     /// - 255 (full forward)
     Mode0,
-    /// The speed registers are interpreted as signed values:  
+    /// The speed registers are interpreted as signed values:
     /// - -128 (full reverse)
     /// - 0 (stop)
     /// - 127 (full forward)
     Mode1,
+    /// Turn mode. The Speed register drives both motors together, and the
+    /// Turn register steers by speeding up one motor and slowing the
+    /// other. Speed and turn are unsigned, as in [`Mode0`](Self::Mode0):
+    /// - Speed: 0 (full reverse) / 128 (stop) / 255 (full forward)
+    /// - Turn: 0 (full left) / 128 (straight) / 255 (full right)
+    Mode2,
+    /// Turn mode, same as [`Mode2`](Self::Mode2) but with signed values, as
+    /// in [`Mode1`](Self::Mode1):
+    /// - Speed: -128 (full reverse) / 0 (stop) / 127 (full forward)
+    /// - Turn: -128 (full left) / 0 (straight) / 127 (full right)
+    Mode3,
 
 }
 
@@ -65,161 +94,128 @@ impl OperatingMode {
         self as u8
     }
 
+    /// Whether `set_turn` performs differential steering in this mode. In
+    /// [`Mode2`](Self::Mode2) and [`Mode3`](Self::Mode3), `set_speed` drives
+    /// both motors together and `set_turn` steers between them; in
+    /// [`Mode0`](Self::Mode0) and [`Mode1`](Self::Mode1) the two registers
+    /// instead control the left and right motor independently.
     pub fn is_turn_mode(&self) -> bool {
         match self {
             OperatingMode::Mode0 | OperatingMode::Mode1 => false,
-            _ => true,
+            OperatingMode::Mode2 | OperatingMode::Mode3 => true,
         }
     }
-}
-
-/// MD22 Driver
-pub struct Md22<I2C> {
-    /// Comment above struct member
-    i2c: I2C,
-    mode: u8,
-    address: u8,
-}
 
-impl<I2C, E> Md22<I2C>
-where
-    I2C: WriteRead<Error = E> + Write<Error = E>,
-    E: Debug,
-{
-    /// Create a new MD22 driver from the given I2C peripheral and mode.  
-    /// Defaults the speed, acceleration, and turn registers to 0.
-    pub fn new(i2c: I2C, mode: OperatingMode, address: I2CAddressSwitchState) -> Result<Self, E> {
-        let mut md22 = Md22 {
-            i2c: i2c,
-            mode: mode.bits(),
-            address: address.bits()
-        };
-        md22.set_mode(mode)?;
-        md22.set_acceleration(0)?;
-        md22.set_speed(0)?;
-        md22.set_turn(0)?;
-
-        Ok(md22)
+    /// Whether the Speed/Turn registers are interpreted as signed values
+    /// in this mode ([`Mode1`](Self::Mode1)/[`Mode3`](Self::Mode3)), as
+    /// opposed to the unsigned 0..255 encoding used by
+    /// [`Mode0`](Self::Mode0)/[`Mode2`](Self::Mode2).
+    pub fn is_signed(&self) -> bool {
+        match self {
+            OperatingMode::Mode0 | OperatingMode::Mode2 => false,
+            OperatingMode::Mode1 | OperatingMode::Mode3 => true,
+        }
     }
 
-    /// Set the operating mode.
-    pub fn set_mode(&mut self, mode: OperatingMode) -> Result<(), E> {
-        let bytes = [Register::Mode.addr(), mode as u8];
-        self.i2c.write(self.address, &bytes)?;
-        Ok(())
+    /// The Speed/Turn register value that means "stop" in this mode: `0`
+    /// is full reverse in the unsigned modes ([`Mode0`](Self::Mode0)/
+    /// [`Mode2`](Self::Mode2)), where stop is `128`; in the signed modes
+    /// ([`Mode1`](Self::Mode1)/[`Mode3`](Self::Mode3)) stop is `0`.
+    pub fn stop_byte(&self) -> u8 {
+        if self.is_signed() { 0 } else { 128 }
     }
+}
 
-    /// Set the motor speed register to the specified value.
-    pub fn set_speed(&mut self, speed: u8) -> Result<(), E> {
-        let bytes = [Register::Speed.addr(), speed];
-        self.i2c.write(self.address, &bytes)?;
-        Ok(())
-    }
+/// A Speed or Turn register value, tagged with the encoding it uses.
+/// Pass the variant matching the driver's current [`OperatingMode`] to
+/// `set_speed_value`/`set_turn_value` (or use `set_speed_signed`/
+/// `set_turn_signed` as shorthand in Mode1/Mode3) -- mixing a signed value
+/// with an unsigned mode, or vice versa, returns
+/// [`SpeedModeError::ModeMismatch`] instead of silently writing the wrong
+/// byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    /// 0 (full reverse) / 128 (stop) / 255 (full forward), as used in
+    /// [`Mode0`](OperatingMode::Mode0)/[`Mode2`](OperatingMode::Mode2).
+    Unsigned(u8),
+    /// -128 (full reverse) / 0 (stop) / 127 (full forward), as used in
+    /// [`Mode1`](OperatingMode::Mode1)/[`Mode3`](OperatingMode::Mode3).
+    Signed(i8),
+}
 
-    /// Set the motor turn register to the specified value.
-    pub fn set_turn(&mut self, turn: u8) -> Result<(), E> {
-        let bytes = [Register::Turn.addr(), turn];
-        self.i2c.write(self.address, &bytes)?;
-        Ok(())
+impl Speed {
+    pub(crate) fn is_signed(&self) -> bool {
+        matches!(self, Speed::Signed(_))
     }
 
-    /// Set the motor acceleration register to the specified value.  
-    /// The acceleration time is given by this value * 64us * n_steps.
-    pub fn set_acceleration(&mut self, acceleration: u8) -> Result<(), E> {
-        let bytes = [Register::Acceleration.addr(), acceleration];
-        self.i2c.write(self.address, &bytes)?;
-        Ok(())
-    }
-    
-    pub fn get_software_revision(&mut self) -> Result<u8, E> {
-        let bytes = [Register::SoftwareRevision.addr()];
-        let mut buffer: [u8;1] = [0;1];
-        self.i2c.write_read(self.address, &bytes, &mut buffer)?;
-        Ok(buffer[0])
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            Speed::Unsigned(v) => v,
+            Speed::Signed(v) => v as u8,
+        }
     }
 }
 
+/// Error returned when setting a [`Speed`] value whose signedness doesn't
+/// match the driver's current [`OperatingMode`].
+#[derive(Debug)]
+pub enum SpeedModeError<E> {
+    /// The underlying I2C transaction failed.
+    Bus(E),
+    /// A [`Speed::Signed`] value was given while in an unsigned mode, or a
+    /// [`Speed::Unsigned`] value was given while in a signed mode.
+    ModeMismatch,
+}
 
-#[cfg(test)]
-mod tests {
-
-    
-    use crate::*;
-
-    use embedded_hal_mock as hal;
-
-    #[test]
-    fn get_software_revision() {
-        let address = I2CAddressSwitchState::OnOnOnOn;
-        let expectation = [
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 0]),
-            hal::i2c::Transaction::write_read(address.bits(), vec![Register::SoftwareRevision.addr()], vec![255]),
-        ];
-        let i2c = hal::i2c::Mock::new(&expectation);
-        let mut md22 = Md22::new(i2c, OperatingMode::Mode0, address).unwrap();
-        assert_eq!(255, md22.get_software_revision().unwrap());
+impl<E> From<E> for SpeedModeError<E> {
+    fn from(e: E) -> Self {
+        SpeedModeError::Bus(e)
     }
+}
 
-    #[test]
-    fn set_mode() {
-        let address = I2CAddressSwitchState::OnOnOnOn;
-        let expectation = [
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), OperatingMode::Mode1.bits()]),
-        ];
-        let i2c = hal::i2c::Mock::new(&expectation);
-        let mut md22 = Md22::new(i2c, OperatingMode::Mode0, address).unwrap();
-        md22.set_mode(OperatingMode::Mode1).unwrap();
-    }
-    
-    #[test]
-    fn set_acceleration() {
-        let address = I2CAddressSwitchState::OnOnOnOn;
-        let expectation = [
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 255]),
-        ];
-        let i2c = hal::i2c::Mock::new(&expectation);
-        let mut md22 = Md22::new(i2c, OperatingMode::Mode0, address).unwrap();
-        md22.set_acceleration(255).unwrap();
-    }
+/// The software revision values a genuine MD22 is expected to report.
+/// Readings outside this range (e.g. 0x00 or 0xFF, typical of a floating
+/// or stuck bus) are treated as [`Md22Error::UnexpectedDevice`].
+pub(crate) const PLAUSIBLE_SOFTWARE_REVISION: core::ops::RangeInclusive<u8> = 1..=0xFE;
+
+/// Error returned by the checked constructors (`new_checked`,
+/// `with_mutex_checked`), layering device-level bring-up faults on top of
+/// the raw transport error `E`. `recover` returns the transport error `E`
+/// itself.
+#[derive(Debug)]
+pub enum Md22Error<E> {
+    /// The underlying I2C transaction failed.
+    Bus(E),
+    /// The device did not acknowledge the software revision read-back
+    /// performed during construction.
+    NotResponding,
+    /// A device responded, but its reported software revision is outside
+    /// the range a genuine MD22 would report.
+    UnexpectedDevice,
+}
 
-    #[test]
-    fn set_speed() {
-        let address = I2CAddressSwitchState::OnOnOnOn;
-        let expectation = [
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 255]),
-        ];
-        let i2c = hal::i2c::Mock::new(&expectation);
-        let mut md22 = Md22::new(i2c, OperatingMode::Mode0, address).unwrap();
-        md22.set_speed(255).unwrap();
+impl<E> From<E> for Md22Error<E> {
+    fn from(e: E) -> Self {
+        Md22Error::Bus(e)
     }
+}
 
-    #[test]
-    fn set_turn() {
-        let address = I2CAddressSwitchState::OnOnOnOn;
-        let expectation = [
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Mode.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Acceleration.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Speed.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 0]),
-            hal::i2c::Transaction::write(address.bits(), vec![Register::Turn.addr(), 255]),
-        ];
-        let i2c = hal::i2c::Mock::new(&expectation);
-        let mut md22 = Md22::new(i2c, OperatingMode::Mode0, address).unwrap();
-        md22.set_turn(255).unwrap();
-    }
+/// MD22 Driver
+///
+/// Constructed and driven via the HAL-version-specific impl blocks in
+/// [`eh0_2`] and [`eh1_0`], so the register logic below is shared across
+/// every embedded-hal generation (the async driver in [`asynch`] owns its
+/// I2C peripheral directly and has its own struct).
+///
+/// Borrows a [`mutex::PortMutex`] `M` that the caller builds and owns, so
+/// the same bus can be lent out to several `Md22`s at different switch
+/// addresses (or to other drivers) by taking `&M` more than once. Use
+/// `Md22::new` (defined in [`eh0_2`] or [`eh1_0`]) for a plain
+/// [`RefCell`](core::cell::RefCell)-backed mutex, or `with_mutex` to plug
+/// in a different `M` for RTIC/threaded use.
+#[cfg(any(feature = "eh0_2", feature = "eh1_0"))]
+pub struct Md22<'a, M> {
+    mutex: &'a M,
+    mode: OperatingMode,
+    address: u8,
 }
@@ -0,0 +1,36 @@
+//! Mutex abstraction for sharing the I2C bus with other devices.
+//!
+//! Mirrors the `PortMutex` pattern used by [port-expander], so an `Md22`
+//! can sit on the same bus as sensors, other MD22 boards, or a port
+//! expander instead of owning the peripheral exclusively.
+//!
+//! [port-expander]: https://docs.rs/port-expander
+
+use core::cell::RefCell;
+
+/// A mutex that owns a `Port` (here, the shared I2C peripheral) and lends
+/// it out for the duration of a single transaction.
+pub trait PortMutex {
+    /// The wrapped resource, e.g. the I2C peripheral.
+    type Port;
+
+    /// Wrap `port` in a new mutex.
+    fn create(port: Self::Port) -> Self;
+
+    /// Lock the mutex for the duration of `f`, giving it exclusive access
+    /// to the wrapped port.
+    fn lock<R>(&self, f: impl FnOnce(&mut Self::Port) -> R) -> R;
+}
+
+impl<T> PortMutex for RefCell<T> {
+    type Port = T;
+
+    fn create(port: T) -> Self {
+        RefCell::new(port)
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut port = self.borrow_mut();
+        f(&mut port)
+    }
+}
@@ -20,10 +20,10 @@ impl Register {
         self as u8
     }
 
+    /// Whether the device rejects writes to this register. Checked by
+    /// each driver's `write_register` as a guard against ever writing
+    /// [`SoftwareRevision`](Self::SoftwareRevision).
     pub fn is_read_only(self) -> bool {
-        match self {
-            Register::SoftwareRevision => true,
-            _ => false,
-        }
+        matches!(self, Register::SoftwareRevision)
     }
 }
\ No newline at end of file